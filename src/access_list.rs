@@ -0,0 +1,73 @@
+//! EIP-2930 access-list generation via a recording [Inspector].
+//!
+//! Ethereum clients expose `eth_createAccessList`, which dry-runs a transaction and
+//! reports every address and storage slot it touches. Warming those slots up front
+//! (the EIP-2718/EIP-2930 typed transaction) lowers the gas charged for the first
+//! access of each account/slot, which matters for MEV and arbitrage bundles.
+//!
+//! [AccessListInspector] reproduces that logic locally: it records every address
+//! entered through a `call` or referenced by a `selfdestruct`, and every slot read by
+//! an `SLOAD`, then deduplicates them into an [AccessList].
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use revm::interpreter::{opcode, CallInputs, CallOutcome, Interpreter};
+use revm::primitives::{Address, U256};
+use revm::{Database, EvmContext, Inspector};
+
+/// An EIP-2930 access list: a set of addresses, each with the storage slots accessed
+/// under it. Laid out exactly as `revm`'s `TxEnv::access_list` expects.
+pub type AccessList = Vec<(Address, Vec<U256>)>;
+
+/// Records the addresses and storage slots touched during execution.
+#[derive(Debug, Default)]
+pub struct AccessListInspector {
+    /// Addresses entered via `call` or named by `selfdestruct`.
+    addresses: BTreeSet<Address>,
+    /// Per-address set of storage slots read by `SLOAD`.
+    slots: BTreeMap<Address, BTreeSet<U256>>,
+}
+
+impl AccessListInspector {
+    /// Consumes the inspector and returns the deduplicated [AccessList].
+    pub fn into_access_list(self) -> AccessList {
+        let mut keys: BTreeSet<Address> = self.addresses;
+        keys.extend(self.slots.keys().copied());
+        keys.into_iter()
+            .map(|address| {
+                let slots = self
+                    .slots
+                    .get(&address)
+                    .map(|s| s.iter().copied().collect())
+                    .unwrap_or_default();
+                (address, slots)
+            })
+            .collect()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListInspector {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        if interp.current_opcode() == opcode::SLOAD {
+            if let Ok(slot) = interp.stack().peek(0) {
+                let address = interp.contract().target_address;
+                self.slots.entry(address).or_default().insert(slot);
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.addresses.insert(inputs.target_address);
+        self.slots.entry(inputs.target_address).or_default();
+        None
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, _value: U256) {
+        self.addresses.insert(contract);
+        self.addresses.insert(target);
+    }
+}
@@ -0,0 +1,176 @@
+//! Ordered multi-transaction bundle simulation with journaled revert points.
+//!
+//! [crate::sim_call] runs a single call; arbitrage and liquidation research needs to
+//! play an ordered bundle (`approve → swap → swap → transfer`) against one shared
+//! state and unwind cleanly when a later call fails. [SimulationBundle] wraps a forked
+//! [Evm] and executes a [`Vec`] of [BundleCall]s sequentially, committing each to the
+//! shared state, accumulating per-call [SimulationResult]s and a total gas figure, and
+//! stopping at the first revert.
+//!
+//! [SimulationBundle::snapshot]/[SimulationBundle::revert_to] clone the underlying
+//! [ForkDB] so callers can branch-and-try alternative tail transactions without
+//! re-forking from RPC, and [SimulationBundle::net_profit] diffs a beneficiary's token
+//! balance across a bundle using the existing `balanceOf`/[to_readable] machinery.
+
+use ethers::prelude::*;
+
+use revm::primitives::{Bytes as rBytes, TransactTo};
+use revm::Evm;
+
+use crate::forked_db::{*, fork_db::ForkDB};
+use crate::{encode_balance_of, to_readable, SimulationResult};
+
+/// A single call in a bundle.
+#[derive(Debug, Clone)]
+pub struct BundleCall {
+    pub caller: Address,
+    pub target: Address,
+    pub call_data: Bytes,
+    pub value: U256,
+}
+
+/// The aggregate result of executing a bundle.
+///
+/// ## Fields
+///
+/// - `results`: The [SimulationResult] of every call that was executed
+///
+/// - `total_gas_used`: The sum of the gas used across the executed calls
+///
+/// - `reverted_at`: The index of the first call that reverted, if any
+#[derive(Debug, Clone)]
+pub struct BundleResult {
+    pub results: Vec<SimulationResult>,
+    pub total_gas_used: u64,
+    pub reverted_at: Option<usize>,
+}
+
+/// A cloned copy of the bundle's state, taken by [SimulationBundle::snapshot].
+pub struct BundleSnapshot {
+    db: ForkDB,
+}
+
+/// The difference in a beneficiary's token balance across a bundle.
+#[derive(Debug, Clone)]
+pub struct NetProfit {
+    pub token: Address,
+    pub balance_before: U256,
+    pub balance_after: U256,
+}
+
+impl NetProfit {
+    /// Whether the bundle increased (or left unchanged) the beneficiary's balance.
+    pub fn is_profit(&self) -> bool {
+        self.balance_after >= self.balance_before
+    }
+
+    /// The absolute size of the balance change.
+    pub fn delta(&self) -> U256 {
+        if self.is_profit() {
+            self.balance_after - self.balance_before
+        } else {
+            self.balance_before - self.balance_after
+        }
+    }
+
+    /// The signed change rendered in token units, e.g. `+1.2500 WETH`.
+    pub fn to_readable(&self) -> String {
+        let sign = if self.is_profit() { "+" } else { "-" };
+        format!("{}{}", sign, to_readable(self.delta(), self.token))
+    }
+}
+
+/// Executes an ordered bundle of calls against one shared [ForkDB].
+pub struct SimulationBundle {
+    evm: Evm<'static, (), ForkDB>,
+}
+
+impl SimulationBundle {
+    /// Wraps an [Evm] whose state changes are committed as the bundle runs.
+    pub fn new(evm: Evm<'static, (), ForkDB>) -> Self {
+        Self { evm }
+    }
+
+    /// Runs `calls` in order, committing each to the shared state.
+    ///
+    /// Execution stops at the first call that reverts; its index is reported in
+    /// [BundleResult::reverted_at] and no later call is attempted.
+    pub fn execute(&mut self, calls: Vec<BundleCall>) -> Result<BundleResult, anyhow::Error> {
+        let mut results = Vec::with_capacity(calls.len());
+        let mut total_gas_used = 0u64;
+        let mut reverted_at = None;
+
+        for (index, call) in calls.into_iter().enumerate() {
+            let result = self.run_call(&call)?;
+            total_gas_used += result.gas_used;
+            let is_reverted = result.is_reverted;
+            results.push(result);
+
+            if is_reverted {
+                reverted_at = Some(index);
+                break;
+            }
+        }
+
+        Ok(BundleResult { results, total_gas_used, reverted_at })
+    }
+
+    /// Clones the current state so it can be restored later with [Self::revert_to].
+    pub fn snapshot(&self) -> BundleSnapshot {
+        BundleSnapshot { db: self.evm.db().clone() }
+    }
+
+    /// Restores the state captured by a previous [Self::snapshot], discarding any
+    /// commits made since, so an alternative tail can be tried without re-forking.
+    pub fn revert_to(&mut self, snapshot: BundleSnapshot) {
+        *self.evm.db_mut() = snapshot.db;
+    }
+
+    /// Diffs `beneficiary`'s `token` balance before and after running `calls`.
+    ///
+    /// The bundle is executed (committing to the shared state); use [Self::snapshot]
+    /// beforehand if the measurement should not persist.
+    pub fn net_profit(
+        &mut self,
+        token: Address,
+        beneficiary: Address,
+        calls: Vec<BundleCall>,
+    ) -> Result<NetProfit, anyhow::Error> {
+        let balance_before = self.balance_of(token, beneficiary)?;
+        self.execute(calls)?;
+        let balance_after = self.balance_of(token, beneficiary)?;
+
+        Ok(NetProfit { token, balance_before, balance_after })
+    }
+
+    /// Commits a single call and packages it as a [SimulationResult].
+    fn run_call(&mut self, call: &BundleCall) -> Result<SimulationResult, anyhow::Error> {
+        self.evm.tx_mut().caller = call.caller.0.into();
+        self.evm.tx_mut().transact_to = TransactTo::Call(call.target.0.into());
+        self.evm.tx_mut().data = rBytes::from(call.call_data.clone().0);
+        self.evm.tx_mut().value = to_revm_u256(call.value);
+
+        let result = self.evm.transact_commit()?;
+
+        Ok(SimulationResult {
+            is_reverted: match_output_reverted(&result),
+            logs: result.logs().to_vec(),
+            gas_used: result.gas_used(),
+            output: result.into_output().unwrap_or_default(),
+            access_list: Vec::new(),
+        })
+    }
+
+    /// Reads `token.balanceOf(holder)` without committing any state.
+    fn balance_of(&mut self, token: Address, holder: Address) -> Result<U256, anyhow::Error> {
+        let call_data = encode_balance_of(holder);
+
+        self.evm.tx_mut().caller = holder.0.into();
+        self.evm.tx_mut().transact_to = TransactTo::Call(token.0.into());
+        self.evm.tx_mut().data = rBytes::from(call_data);
+        self.evm.tx_mut().value = to_revm_u256(U256::zero());
+
+        let output = self.evm.transact()?.result.into_output().unwrap_or_default();
+        Ok(U256::from_big_endian(output.as_ref()))
+    }
+}
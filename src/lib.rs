@@ -1,13 +1,19 @@
 pub mod forked_db;
+pub mod sqlite_cache;
+pub mod access_list;
+pub mod bundle;
 
-use ethers::{prelude::*, abi::{parse_abi, Abi}, utils::{parse_ether, keccak256}};
-use ethabi::Token;
-use std::sync::Arc;
+use ethers::{prelude::*, abi::Abi, utils::{parse_ether, keccak256}};
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
 use std::str::FromStr;
 use forked_db::{*, fork_factory::ForkFactory, fork_db::ForkDB};
+use alloy_sol_types::{sol, SolCall};
+use sqlite_cache::SqliteCache;
+use access_list::{AccessList, AccessListInspector};
 
 use revm::primitives::{Bytecode, Bytes as rBytes, Address as rAddress, B256, AccountInfo, TransactTo, Log};
-use revm::Evm;
+use revm::{Evm, Database, DatabaseRef, DatabaseCommit};
 use bigdecimal::BigDecimal;
 use lazy_static::lazy_static;
 use serde_json::Value;
@@ -17,6 +23,39 @@ lazy_static!{
     pub static ref WETH: Address = Address::from_str("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2").unwrap();
     pub static ref USDT: Address = Address::from_str("0xdAC17F958D2ee523a2206206994597C13D831ec7").unwrap();
     pub static ref USDC: Address = Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+
+    /// Balance mapping slots discovered by [deal], keyed by token. The boolean marks a
+    /// Vyper-style `keccak256(slot, holder)` layout as opposed to Solidity's
+    /// `keccak256(holder, slot)`.
+    static ref BALANCE_SLOTS: Mutex<HashMap<Address, (u32, bool)>> = Mutex::new(HashMap::new());
+}
+
+sol! {
+    /// Mirror of the Solidity `SwapParams` struct consumed by the router's
+    /// `do_swap` function. Deriving it through `sol!` keeps the field order
+    /// (and therefore the ABI encoding) in lock-step with the contract.
+    struct SwapParamsSol {
+        address input_token;
+        address output_token;
+        uint256 amount_in;
+        address pool;
+        uint256 pool_variant;
+        uint256 minimum_received;
+    }
+
+    /// Router entry point used by [encode_swap]/[decode_swap].
+    function do_swap(SwapParamsSol params) external returns (uint256 amount_out);
+
+    /// Minimal ERC20 surface used by the encoders.
+    function approve(address spender, uint256 amount) external returns (bool);
+    function transfer(address recipient, uint256 amount) external returns (bool);
+    function balanceOf(address account) external view returns (uint256);
+
+    /// Router helper that sweeps a stuck ERC20 balance.
+    function recover_erc20(address token, uint256 amount) external;
+
+    /// WETH wrapping entry point.
+    function deposit() external payable;
 }
 
 /// Parameters used for a swap
@@ -31,19 +70,17 @@ pub struct SwapParams {
 }
 
 impl SwapParams {
-    pub fn to_tokens(&self) -> Vec<Token> {
-        vec![
-            Token::Tuple(
-                vec![
-                    Token::Address(self.input_token),
-                    Token::Address(self.output_token),
-                    Token::Uint(self.amount_in),
-                    Token::Address(self.pool),
-                    Token::Uint(self.pool_variant),
-                    Token::Uint(self.minimum_received)
-                ]
-            )
-        ]
+    /// Converts the parameters into the `sol!`-generated [SwapParamsSol], so the
+    /// argument tuple is laid out by the compiler rather than by hand.
+    pub fn to_sol(&self) -> SwapParamsSol {
+        SwapParamsSol {
+            input_token: self.input_token.0.into(),
+            output_token: self.output_token.0.into(),
+            amount_in: to_revm_u256(self.amount_in),
+            pool: self.pool.0.into(),
+            pool_variant: to_revm_u256(self.pool_variant),
+            minimum_received: to_revm_u256(self.minimum_received),
+        }
     }
 }
 
@@ -68,25 +105,37 @@ pub enum AccountType {
 /// - `value`: The amount of ETH to send with the transaction
 /// 
 /// - `apply_changes`: Whether to apply the state changes or not to [Evm]
-/// 
+///
+/// - `access_list`: An EIP-2930 access list injected into the transaction
+///
+/// - `generate_access_list`: When `true`, [sim_call] derives the access list from a dry
+///   run (via [create_access_list]) and reports it in [SimulationResult::access_list]
+///   instead of echoing back the injected one
+///
 /// - `evm`: The [Evm] instance to use
+///
+/// `DB` defaults to [ForkDB] but is any [Database], so a [SqliteCache]-wrapped fork can
+/// be simulated against directly.
 #[derive(Debug)]
-pub struct EvmParams {
+pub struct EvmParams<DB = ForkDB> {
     pub caller: Address,
     pub transact_to: Address,
     pub call_data: Bytes,
     pub value: U256,
     pub apply_changes: bool,
-    pub evm: Evm<'static, (), ForkDB>
+    pub access_list: AccessList,
+    pub generate_access_list: bool,
+    pub evm: Evm<'static, (), DB>
 }
 
-impl EvmParams {
+impl<DB> EvmParams<DB> {
     /// Sets the transaction environment for the [Evm] instance
     pub fn set_tx_env(&mut self) {
         self.evm.tx_mut().caller = self.caller.0.into();
         self.evm.tx_mut().transact_to = TransactTo::Call(self.transact_to.0.into());
         self.evm.tx_mut().data = rBytes::from(self.call_data.clone().0);
         self.evm.tx_mut().value = to_revm_u256(self.value);
+        self.evm.tx_mut().access_list = self.access_list.clone();
     }
 
     /// Sets the `caller` of the transaction
@@ -114,8 +163,18 @@ impl EvmParams {
         self.apply_changes = apply_changes;
     }
 
+    /// Sets whether [sim_call] should auto-generate the EIP-2930 access list
+    pub fn set_generate_access_list(&mut self, generate_access_list: bool) {
+        self.generate_access_list = generate_access_list;
+    }
+
+    /// Sets the EIP-2930 `access_list` injected into the transaction
+    pub fn set_access_list(&mut self, access_list: AccessList) {
+        self.access_list = access_list;
+    }
+
     /// Sets the [Evm] instance
-    pub fn set_evm(&mut self, evm: Evm<'static, (), ForkDB>) {
+    pub fn set_evm(&mut self, evm: Evm<'static, (), DB>) {
         self.evm = evm;
     }
 }
@@ -131,12 +190,16 @@ impl EvmParams {
 /// - `gas_used`: The amount of gas was used
 /// 
 /// - `output`: The output of the call (If the function of the contract returns a value)
+///
+/// - `access_list`: The EIP-2930 access list for the call — the one generated by the
+///   dry run when [EvmParams::generate_access_list] is set, otherwise the injected one
 #[derive(Debug, Clone)]
 pub struct SimulationResult {
     pub is_reverted: bool,
     pub logs: Vec<Log>,
     pub gas_used: u64,
     pub output: rBytes,
+    pub access_list: AccessList,
 }
 
 
@@ -177,7 +240,7 @@ pub async fn get_client() -> Result<Arc<Provider<Ws>>, anyhow::Error> {
 /// Creates a new [Evm] instance with initial state from [ForkDB]
 /// 
 /// State changes are applied to [Evm]
-pub fn new_evm(fork_db: ForkDB, block: Block<H256>) -> Evm<'static, (), ForkDB> {
+pub fn new_evm<DB: Database>(fork_db: DB, block: Block<H256>) -> Evm<'static, (), DB> {
     let mut evm = Evm::builder().with_db(fork_db).build();
 
     let evm_block = U256::from(block.number.unwrap().as_u64());
@@ -197,19 +260,59 @@ pub fn new_evm(fork_db: ForkDB, block: Block<H256>) -> Evm<'static, (), ForkDB>
 
 
 
+/// Extends [ForkFactory] with a disk-backed cache constructor.
+///
+/// Pin a block once and then run thousands of `sim_call`s offline: the first access of
+/// each account/slot falls through to the provider and is written to SQLite, every
+/// later access is served locally. See [SqliteCache].
+pub trait WithSqliteCache {
+    /// Wraps a fresh sandbox fork in a [SqliteCache] stored at `path`, keyed by
+    /// `fork_block`.
+    fn with_sqlite_cache(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        fork_block: u64,
+    ) -> Result<SqliteCache<ForkDB>, anyhow::Error>;
+}
+
+impl WithSqliteCache for ForkFactory {
+    fn with_sqlite_cache(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        fork_block: u64,
+    ) -> Result<SqliteCache<ForkDB>, anyhow::Error> {
+        SqliteCache::new(path, fork_block, self.new_sandbox_fork())
+    }
+}
+
 /// Simulates a call with the given [EvmParams]
 /// 
 /// ## Returns
 ///
 /// [SimulationResult]
-pub fn sim_call(params: &mut EvmParams) -> Result<SimulationResult, anyhow::Error> {
+pub fn sim_call<DB>(params: &mut EvmParams<DB>) -> Result<SimulationResult, anyhow::Error>
+where
+    DB: Database + DatabaseCommit + DatabaseRef + Clone,
+    <DB as Database>::Error: std::fmt::Debug,
+    <DB as DatabaseRef>::Error: std::fmt::Debug,
+{
+    // Derive the access list from a dry run up front, otherwise report the injected one.
+    let access_list = if params.generate_access_list {
+        create_access_list(params)?
+    } else {
+        params.access_list.clone()
+    };
+
     params.set_tx_env();
 
 
    let result = if params.apply_changes {
-        params.evm.transact_commit()?
+        params.evm.transact_commit()
+            .map_err(|e| anyhow::anyhow!("transact_commit failed: {:?}", e))?
     } else {
-        params.evm.transact()?.result
+        params.evm.transact()
+            .map_err(|e| anyhow::anyhow!("transact failed: {:?}", e))?
+            .result
     };
 
     let is_reverted = match_output_reverted(&result);
@@ -222,67 +325,77 @@ pub fn sim_call(params: &mut EvmParams) -> Result<SimulationResult, anyhow::Erro
         logs,
         gas_used,
         output,
+        access_list,
     };
 
     Ok(sim_result)
 }
 
+/// Generates an EIP-2930 access list for the transaction described by `params`,
+/// without committing any state changes.
+///
+/// This is the local equivalent of `eth_createAccessList`: the call is dry-run with
+/// `transact` on a clone of the database, with an [AccessListInspector] registered via
+/// [revm::inspector_handle_register] that records every address entered and every
+/// storage slot read. Feed the result back into [EvmParams::set_access_list] to
+/// warm those slots and measure the gas delta.
+pub fn create_access_list<DB>(params: &EvmParams<DB>) -> Result<AccessList, anyhow::Error>
+where
+    DB: DatabaseRef + Clone,
+    <DB as DatabaseRef>::Error: std::fmt::Debug,
+{
+    let mut inspector = AccessListInspector::default();
+
+    let mut evm = Evm::builder()
+        .with_ref_db(params.evm.db().clone())
+        .with_external_context(&mut inspector)
+        .append_handler_register(revm::inspector_handle_register)
+        .build();
+
+    *evm.block_mut() = params.evm.block().clone();
+    *evm.cfg_mut() = params.evm.cfg().clone();
+    evm.tx_mut().caller = params.caller.0.into();
+    evm.tx_mut().transact_to = TransactTo::Call(params.transact_to.0.into());
+    evm.tx_mut().data = rBytes::from(params.call_data.clone().0);
+    evm.tx_mut().value = to_revm_u256(params.value);
+
+    evm.transact()
+        .map_err(|e| anyhow::anyhow!("access list dry run failed: {:?}", e))?;
+    drop(evm);
+
+    Ok(inspector.into_access_list())
+}
+
 /// Encodes the swap parameters needed for the swap function of the router contract
 pub fn encode_swap(params: SwapParams) -> Vec<u8> {
-    let contract_abi = swap_router_abi();
-    let swap_abi = contract_abi.function("do_swap").unwrap();
-    let tokens = params.to_tokens();
-    let encoded_args = swap_abi.encode_input(&tokens).unwrap();
-    encoded_args
+    do_swapCall { params: params.to_sol() }.abi_encode()
 }
 
 /// Decodes the output of the swap function of the router contract
-/// 
+///
 /// ## Returns
 /// [U256] the real amount received after the swap
 pub fn decode_swap(bytes: Bytes) -> Result<U256, anyhow::Error> {
-    let tokens = swap_router_abi().function("do_swap").unwrap().decode_output(&bytes)?;
-
-    if let Some(Token::Uint(value)) = tokens.get(0) {
-        Ok(value.clone())
-    } else {
-        Err(anyhow::anyhow!("Error decoding amount"))
-    }
+    let decoded = do_swapCall::abi_decode_returns(&bytes, false)?;
+    Ok(to_ethers_u256(decoded.amount_out))
 }
 
 pub fn encode_recover_erc20(
     token: Address,
     amount: U256
 ) -> Vec<u8> {
-    let method_id = &keccak256(b"recover_erc20(address,uint256)")[0..4];
-    
-    let encoded_args = ethabi::encode(
-        &[
-            ethabi::Token::Address(token),
-            ethabi::Token::Uint(amount),
-        ]
-    );
-
-    let mut payload = vec![];
-    payload.extend_from_slice(method_id);
-    payload.extend_from_slice(&encoded_args);
-
-    payload
+    recover_erc20Call {
+        token: token.0.into(),
+        amount: to_revm_u256(amount),
+    }.abi_encode()
 }
 
 /// ERC20 approve function
 pub fn encode_approve(spender: Address, amount: U256) -> Vec<u8> {
-    let method_id = &keccak256(b"approve(address,uint256)")[0..4];
-
-    let encoded_args = ethabi::encode(
-        &[ethabi::Token::Address(spender), ethabi::Token::Uint(amount)]
-    );
-
-    let mut payload = vec![];
-    payload.extend_from_slice(method_id);
-    payload.extend_from_slice(&encoded_args);
-
-    payload
+    approveCall {
+        spender: spender.0.into(),
+        amount: to_revm_u256(amount),
+    }.abi_encode()
 }
 
 /// ERC20 transfer function
@@ -290,20 +403,20 @@ pub fn encode_transfer(
     recipient: Address,
     amount: U256,
 ) -> Vec<u8> {
-    let method_id = &keccak256(b"transfer(address,uint256)")[0..4];
-    
-    let encoded_args = ethabi::encode(
-        &[
-            ethabi::Token::Address(recipient),
-            ethabi::Token::Uint(amount),
-        ]
-    );
+    transferCall {
+        recipient: recipient.0.into(),
+        amount: to_revm_u256(amount),
+    }.abi_encode()
+}
 
-    let mut payload = vec![];
-    payload.extend_from_slice(method_id);
-    payload.extend_from_slice(&encoded_args);
+/// ERC20 balanceOf function
+pub fn encode_balance_of(account: Address) -> Vec<u8> {
+    balanceOfCall { account: account.0.into() }.abi_encode()
+}
 
-    payload
+/// WETH deposit (wrap) function
+pub fn encode_deposit() -> Vec<u8> {
+    depositCall {}.abi_encode()
 }
 
 
@@ -352,6 +465,135 @@ pub fn insert_dummy_account(account_type: AccountType, fork_factory: &mut ForkFa
     Ok(dummy_account.address())
 }
 
+/// Upper bound on the balance mapping slot index probed by [deal]. Real ERC20 layouts
+/// keep `balances` well within the first few dozen storage slots.
+const MAX_BALANCE_SLOT: u32 = 64;
+
+/// Computes the storage key of a balance mapping entry.
+///
+/// Solidity lays a `mapping(address => uint256)` out as `keccak256(holder . slot)`;
+/// Vyper reverses the order to `keccak256(slot . holder)`. `vyper` selects which.
+fn balance_slot_key(holder: Address, slot: u32, vyper: bool) -> U256 {
+    let tokens = if vyper {
+        vec![abi::Token::Uint(U256::from(slot)), abi::Token::Address(holder)]
+    } else {
+        vec![abi::Token::Address(holder), abi::Token::Uint(U256::from(slot))]
+    };
+    keccak256(abi::encode(&tokens)).into()
+}
+
+/// Reads `token.balanceOf(holder)` against a throwaway fork of `fork_factory`.
+fn probe_balance_of(
+    token: Address,
+    holder: Address,
+    fork_factory: &ForkFactory,
+    block: &Block<H256>,
+) -> Result<U256, anyhow::Error> {
+    let fork_db = fork_factory.new_sandbox_fork();
+    let evm = new_evm(fork_db, block.clone());
+    let call_data = Bytes::from(encode_balance_of(holder));
+
+    let mut params = EvmParams {
+        caller: holder,
+        transact_to: token,
+        call_data,
+        value: U256::zero(),
+        apply_changes: false,
+        access_list: Vec::new(),
+        generate_access_list: false,
+        evm,
+    };
+
+    let result = sim_call(&mut params)?;
+    Ok(U256::from_big_endian(result.output.as_ref()))
+}
+
+/// Funds `holder` with `amount` of `token` inside the local fork, discovering the
+/// token's balance mapping slot automatically.
+///
+/// Unlike [insert_dummy_account] — which hard-codes WETH's slot `3` — this probes slot
+/// indices `0..64` in both Solidity (`keccak256(holder, i)`) and Vyper
+/// (`keccak256(i, holder)`) layouts: a sentinel is written at each candidate key and
+/// `balanceOf(holder)` is re-read; the index that echoes the sentinel back is the real
+/// slot. Non-matching probes are restored to their original values, the requested
+/// `amount` is written to the real slot, and the discovered slot is cached so repeated
+/// deals of the same token skip the search entirely.
+///
+/// The first deal of an unknown token is expensive: up to `2 * [MAX_BALANCE_SLOT]`
+/// candidates are probed, each running one `balanceOf` simulation. Original slot values
+/// are read from a single sandbox fork ([Database::storage], which caches within the
+/// fork) rather than re-fetched per candidate, and the cache above makes every
+/// subsequent deal a single write.
+pub fn deal(
+    token: Address,
+    holder: Address,
+    amount: U256,
+    fork_factory: &mut ForkFactory,
+    block: &Block<H256>,
+) -> Result<(), anyhow::Error> {
+    // Fast path: we already know where this token keeps its balances.
+    if let Some(&(slot, vyper)) = BALANCE_SLOTS.lock().unwrap().get(&token) {
+        let key = balance_slot_key(holder, slot, vyper);
+        fork_factory
+            .insert_account_storage(token.0.into(), to_revm_u256(key), to_revm_u256(amount))
+            .map_err(|e| anyhow::anyhow!("Failed to insert account storage: {}", e))?;
+        return Ok(());
+    }
+
+    // A value unlikely to coincide with any genuine on-chain balance.
+    let sentinel = U256::from_dec_str("133713371337133713371337").unwrap();
+
+    // Snapshot taken before any writes so we can recover the original slot values. Read
+    // through `Database::storage` (not `storage_ref`) so the already-forked state serves
+    // and caches the reads instead of re-fetching from RPC on every candidate.
+    let mut original_db = fork_factory.new_sandbox_fork();
+
+    let mut probed: Vec<(U256, U256)> = Vec::new();
+    let mut found: Option<(u32, bool, U256)> = None;
+
+    'search: for vyper in [false, true] {
+        for slot in 0..MAX_BALANCE_SLOT {
+            let key = balance_slot_key(holder, slot, vyper);
+
+            let original = original_db
+                .storage(token.0.into(), to_revm_u256(key))
+                .map_err(|e| anyhow::anyhow!("Failed to read storage: {:?}", e))?;
+            probed.push((key, to_ethers_u256(original)));
+
+            fork_factory
+                .insert_account_storage(token.0.into(), to_revm_u256(key), to_revm_u256(sentinel))
+                .map_err(|e| anyhow::anyhow!("Failed to insert account storage: {}", e))?;
+
+            if probe_balance_of(token, holder, fork_factory, block)? == sentinel {
+                found = Some((slot, vyper, key));
+                break 'search;
+            }
+        }
+    }
+
+    // Restore every probed slot we are not funding, then write the requested amount.
+    let winner_key = found.map(|(_, _, key)| key);
+    for (key, original) in probed {
+        if Some(key) == winner_key {
+            continue;
+        }
+        fork_factory
+            .insert_account_storage(token.0.into(), to_revm_u256(key), to_revm_u256(original))
+            .map_err(|e| anyhow::anyhow!("Failed to insert account storage: {}", e))?;
+    }
+
+    let (slot, vyper, winner) = found.ok_or_else(|| {
+        anyhow::anyhow!("Could not locate the balance slot for token {:?}", token)
+    })?;
+
+    fork_factory
+        .insert_account_storage(token.0.into(), to_revm_u256(winner), to_revm_u256(amount))
+        .map_err(|e| anyhow::anyhow!("Failed to insert account storage: {}", e))?;
+
+    BALANCE_SLOTS.lock().unwrap().insert(token, (slot, vyper));
+    Ok(())
+}
+
 
 
 pub fn to_readable(amount: U256, token: Address) -> String {
@@ -394,14 +636,3 @@ pub fn swap_router_abi() -> Abi {
     serde_json::from_value(value["abi"].clone()).unwrap()
 }
 
-pub fn weth_deposit() -> BaseContract {
-    BaseContract::from(parse_abi(
-        &["function deposit() public payable"]
-    ).unwrap())
-}
-
-pub fn erc20_balanceof() -> BaseContract {
-    BaseContract::from(parse_abi(
-        &["function balanceOf(address) public view returns (uint256)"]
-    ).unwrap())
-}
\ No newline at end of file
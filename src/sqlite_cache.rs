@@ -0,0 +1,350 @@
+//! Disk-backed caching layer for [ForkDB](crate::forked_db::fork_db::ForkDB) state.
+//!
+//! Every simulation that forks from a live RPC (`wss://eth.merkle.io`) re-fetches
+//! account info, bytecode and storage slots on each cold access. When a user pins a
+//! single block and runs thousands of `sim_call`s against it, those fetches are both
+//! slow and rate-limited even though the answers never change for a fixed block.
+//!
+//! [SqliteCache] sits *behind* a [DatabaseRef] (the provider-backed `ForkDB`) and
+//! serves reads from a local SQLite file, modeled on reth's revm-state database. On a
+//! `basic`/`code_by_hash`/`storage` miss it falls through to the wrapped database and
+//! writes the result back, so subsequent runs against the same block serve account,
+//! code and storage reads offline. Block hashes are not cached and are always read from
+//! the wrapped database.
+//!
+//! Account and storage rows are additionally keyed by the fork block number, so caches
+//! for different pinned blocks can coexist in one file without clobbering each other.
+//! Bytecode rows are keyed by code hash alone and are shared across blocks.
+
+use std::sync::Arc;
+
+use revm::primitives::{Account, AccountInfo, Address, Bytecode, Bytes, B256, HashMap, U256};
+use revm::{Database, DatabaseCommit, DatabaseRef};
+use rusqlite::{Connection, OptionalExtension};
+
+/// SQLite-backed write-through cache wrapping a provider-backed database.
+///
+/// The SQLite handle is shared behind an [`Arc`](std::sync::Arc) so the cache can be
+/// cloned (e.g. by [create_access_list](crate::create_access_list) for its dry run) while
+/// every clone keeps writing through to the same file.
+#[derive(Clone)]
+pub struct SqliteCache<ExtDB> {
+    /// The underlying database consulted on a cache miss.
+    inner: ExtDB,
+    /// Block number the fork is pinned to; part of every cache key.
+    fork_block: u64,
+    /// Shared handle to the SQLite file holding the cached state.
+    conn: Arc<Connection>,
+    /// In-memory overlay of state committed through [DatabaseCommit], layered on top of
+    /// the SQLite/provider base. Kept in memory (not persisted) so the on-disk cache
+    /// stays a faithful snapshot of the pinned block while `sim_call`s mutate state.
+    overlay: Overlay,
+}
+
+/// Mutable state accumulated by [DatabaseCommit::commit], read back ahead of the cache.
+#[derive(Clone, Default)]
+struct Overlay {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, U256), U256>,
+}
+
+impl<ExtDB> SqliteCache<ExtDB> {
+    /// Opens (or creates) the SQLite cache at `path` in front of `inner`, pinned to
+    /// `fork_block`. The required tables are created on first use.
+    pub fn new(
+        path: impl AsRef<std::path::Path>,
+        fork_block: u64,
+        inner: ExtDB,
+    ) -> Result<Self, anyhow::Error> {
+        let conn = Connection::open(path)?;
+        // Run in WAL mode so writes are appended to a side log; this is what lets
+        // `flush`'s `wal_checkpoint` actually fold buffered writes back into the file.
+        conn.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()))?;
+        init_schema(&conn)?;
+        Ok(Self {
+            inner,
+            fork_block,
+            conn: Arc::new(conn),
+            overlay: Overlay::default(),
+        })
+    }
+
+    /// Drops every cached row for the pinned block, forcing the next reads to fall
+    /// through to the provider again.
+    pub fn clear(&self) -> Result<(), anyhow::Error> {
+        self.conn.execute("DELETE FROM account WHERE block = ?1", [self.fork_block])?;
+        self.conn.execute("DELETE FROM account_miss WHERE block = ?1", [self.fork_block])?;
+        self.conn.execute("DELETE FROM storage WHERE block = ?1", [self.fork_block])?;
+        // `code` is content-addressed by `code_hash`, so it is shared across blocks
+        // and intentionally left untouched by a per-block clear.
+        Ok(())
+    }
+
+    /// Flushes any buffered writes to disk. SQLite commits per-statement by default,
+    /// so this issues a `wal_checkpoint` to make the data durable outside the WAL.
+    pub fn flush(&self) -> Result<(), anyhow::Error> {
+        self.conn.pragma_update(None, "wal_checkpoint", "TRUNCATE")?;
+        Ok(())
+    }
+}
+
+/// Creates the `account`, `code` and `storage` tables if they do not yet exist.
+fn init_schema(conn: &Connection) -> Result<(), anyhow::Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS account (
+            block     INTEGER NOT NULL,
+            address   BLOB NOT NULL,
+            balance   BLOB NOT NULL,
+            nonce     INTEGER NOT NULL,
+            code_hash BLOB NOT NULL,
+            PRIMARY KEY (block, address)
+         );
+         CREATE TABLE IF NOT EXISTS account_miss (
+            block   INTEGER NOT NULL,
+            address BLOB NOT NULL,
+            PRIMARY KEY (block, address)
+         );
+         CREATE TABLE IF NOT EXISTS code (
+            code_hash BLOB PRIMARY KEY,
+            bytecode  BLOB NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS storage (
+            block   INTEGER NOT NULL,
+            address BLOB NOT NULL,
+            slot    BLOB NOT NULL,
+            value   BLOB NOT NULL,
+            PRIMARY KEY (block, address, slot)
+         );",
+    )?;
+    Ok(())
+}
+
+impl<ExtDB: DatabaseRef> SqliteCache<ExtDB>
+where
+    ExtDB::Error: std::fmt::Debug,
+{
+    fn cached_account(&self, address: Address) -> Result<Option<AccountInfo>, anyhow::Error> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT balance, nonce, code_hash FROM account WHERE block = ?1 AND address = ?2",
+                (self.fork_block, address.as_slice()),
+                |row| {
+                    let balance: Vec<u8> = row.get(0)?;
+                    let nonce: i64 = row.get(1)?;
+                    let code_hash: Vec<u8> = row.get(2)?;
+                    Ok((balance, nonce as u64, code_hash))
+                },
+            )
+            .optional()?;
+
+        let Some((balance, nonce, code_hash)) = row else { return Ok(None) };
+        let code_hash = B256::from_slice(&code_hash);
+        let code = self.cached_code(code_hash)?;
+        Ok(Some(AccountInfo {
+            balance: U256::from_be_slice(&balance),
+            nonce,
+            code_hash,
+            code,
+        }))
+    }
+
+    fn store_account(&self, address: Address, info: &AccountInfo) -> Result<(), anyhow::Error> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO account (block, address, balance, nonce, code_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (
+                self.fork_block,
+                address.as_slice(),
+                info.balance.to_be_bytes::<32>().as_slice(),
+                info.nonce as i64,
+                info.code_hash.as_slice(),
+            ),
+        )?;
+        if let Some(code) = &info.code {
+            self.store_code(info.code_hash, code)?;
+        }
+        Ok(())
+    }
+
+    /// Whether a previous lookup already proved this account empty at the pinned block.
+    fn account_is_missing(&self, address: Address) -> Result<bool, anyhow::Error> {
+        let hit: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT 1 FROM account_miss WHERE block = ?1 AND address = ?2",
+                (self.fork_block, address.as_slice()),
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(hit.is_some())
+    }
+
+    /// Records that `address` does not exist at the pinned block, so later lookups are
+    /// served locally instead of re-hitting the provider.
+    fn store_account_miss(&self, address: Address) -> Result<(), anyhow::Error> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO account_miss (block, address) VALUES (?1, ?2)",
+            (self.fork_block, address.as_slice()),
+        )?;
+        Ok(())
+    }
+
+    fn cached_code(&self, code_hash: B256) -> Result<Option<Bytecode>, anyhow::Error> {
+        let bytes: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT bytecode FROM code WHERE code_hash = ?1",
+                [code_hash.as_slice()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(bytes.map(|b| Bytecode::new_raw(Bytes::from(b))))
+    }
+
+    fn store_code(&self, code_hash: B256, code: &Bytecode) -> Result<(), anyhow::Error> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO code (code_hash, bytecode) VALUES (?1, ?2)",
+            (code_hash.as_slice(), code.bytes().as_ref()),
+        )?;
+        Ok(())
+    }
+
+    fn cached_storage(&self, address: Address, slot: U256) -> Result<Option<U256>, anyhow::Error> {
+        let value: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT value FROM storage WHERE block = ?1 AND address = ?2 AND slot = ?3",
+                (self.fork_block, address.as_slice(), slot.to_be_bytes::<32>().as_slice()),
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value.map(|v| U256::from_be_slice(&v)))
+    }
+
+    fn store_storage(&self, address: Address, slot: U256, value: U256) -> Result<(), anyhow::Error> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO storage (block, address, slot, value) VALUES (?1, ?2, ?3, ?4)",
+            (
+                self.fork_block,
+                address.as_slice(),
+                slot.to_be_bytes::<32>().as_slice(),
+                value.to_be_bytes::<32>().as_slice(),
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+impl<ExtDB: DatabaseRef> DatabaseRef for SqliteCache<ExtDB>
+where
+    ExtDB::Error: std::fmt::Debug,
+{
+    type Error = anyhow::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.overlay.accounts.get(&address) {
+            return Ok(Some(info.clone()));
+        }
+        if let Some(info) = self.cached_account(address)? {
+            return Ok(Some(info));
+        }
+        if self.account_is_missing(address)? {
+            return Ok(None);
+        }
+        let info = self
+            .inner
+            .basic_ref(address)
+            .map_err(|e| anyhow::anyhow!("provider basic miss: {e:?}"))?;
+        match &info {
+            // Cache the negative hit too, otherwise empty accounts re-hit the provider
+            // on every run and defeat the offline promise.
+            Some(info) => self.store_account(address, info)?,
+            None => self.store_account_miss(address)?,
+        }
+        Ok(info)
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if let Some(code) = self.cached_code(code_hash)? {
+            return Ok(code);
+        }
+        let code = self
+            .inner
+            .code_by_hash_ref(code_hash)
+            .map_err(|e| anyhow::anyhow!("provider code miss: {e:?}"))?;
+        self.store_code(code_hash, &code)?;
+        Ok(code)
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self.overlay.storage.get(&(address, index)) {
+            return Ok(*value);
+        }
+        if let Some(value) = self.cached_storage(address, index)? {
+            return Ok(value);
+        }
+        let value = self
+            .inner
+            .storage_ref(address, index)
+            .map_err(|e| anyhow::anyhow!("provider storage miss: {e:?}"))?;
+        self.store_storage(address, index, value)?;
+        Ok(value)
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.inner
+            .block_hash_ref(number)
+            .map_err(|e| anyhow::anyhow!("provider block_hash miss: {e:?}"))
+    }
+}
+
+/// The write-through cache only ever reads through `&self`, so [Database] simply
+/// forwards to the [DatabaseRef] implementation.
+impl<ExtDB: DatabaseRef> Database for SqliteCache<ExtDB>
+where
+    ExtDB::Error: std::fmt::Debug,
+{
+    type Error = anyhow::Error;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.basic_ref(address)
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.code_by_hash_ref(code_hash)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.storage_ref(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hash_ref(number)
+    }
+}
+
+/// State committed by `transact_commit` lands in the in-memory [Overlay] rather than in
+/// the SQLite file: the cache stays a clean snapshot of the pinned block while a
+/// sequence of `sim_call`s mutates simulated state on top of it.
+impl<ExtDB: DatabaseRef> DatabaseCommit for SqliteCache<ExtDB>
+where
+    ExtDB::Error: std::fmt::Debug,
+{
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        for (address, account) in changes {
+            if !account.is_touched() {
+                continue;
+            }
+            if account.is_selfdestructed() {
+                self.overlay.accounts.insert(address, AccountInfo::default());
+                self.overlay.storage.retain(|(a, _), _| *a != address);
+                continue;
+            }
+            self.overlay.accounts.insert(address, account.info.clone());
+            for (slot, value) in account.storage {
+                self.overlay.storage.insert((address, slot), value.present_value());
+            }
+        }
+    }
+}